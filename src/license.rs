@@ -20,7 +20,7 @@ use ngram::NgramSet;
 use preproc::{apply_aggressive, apply_normalizers};
 
 /// The type of a license entry (typically in a `Store`).
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum LicenseType {
     /// The canonical text of the license.
     Original,
@@ -46,6 +46,25 @@ impl fmt::Display for LicenseType {
     }
 }
 
+/// The largest DP table `lcs_diff` is allowed to build for `TextData::diff`,
+/// in cells (`(tokens_in_a + 1) * (tokens_in_b + 1)`). `lcs_diff` is O(n*m)
+/// in both time and space, so this bounds the diff to a few hundred
+/// megabytes of `u32` table in the worst case rather than letting an
+/// unnarrowed, file-sized `TextData` exhaust memory.
+const MAX_DIFF_TABLE_CELLS: u64 = 64 * 1024 * 1024;
+
+/// A single operation in a token-level diff between two `TextData`, as
+/// returned by `TextData::diff`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum DiffOp {
+    /// A token present, unchanged, in both texts.
+    Equal(String),
+    /// A token present in `self` but not in the text it was diffed against.
+    Insert(String),
+    /// A token present in the other text but not in `self`.
+    Delete(String),
+}
+
 /// A structure representing compiled text/matching data.
 ///
 /// This is the key structure used to compare two texts against one another. It
@@ -88,6 +107,7 @@ pub struct TextData {
     lines_view: (usize, usize),
     lines_normalized: Option<Vec<String>>,
     text_processed: Option<String>,
+    copyright_lines: Option<Vec<String>>,
 }
 
 impl TextData {
@@ -101,7 +121,7 @@ impl TextData {
     /// this library you want to keep the text data, but askalono will throw it
     /// away in its own `Store` as it's not needed.
     pub fn new(text: &str) -> TextData {
-        let normalized = apply_normalizers(text);
+        let (normalized, copyright_lines) = apply_normalizers(text);
         let normalized_joined = normalized.join("\n");
         let processed = apply_aggressive(&normalized_joined);
         let match_data = NgramSet::from_str(&processed, 2);
@@ -111,6 +131,7 @@ impl TextData {
             lines_view: (0, normalized.len()),
             lines_normalized: Some(normalized),
             text_processed: Some(processed),
+            copyright_lines: Some(copyright_lines),
         }
     }
 
@@ -130,6 +151,7 @@ impl TextData {
             lines_view: (0, 0),
             lines_normalized: None,
             text_processed: None,
+            copyright_lines: None,
         }
     }
 
@@ -167,6 +189,7 @@ impl TextData {
             lines_view: (start, end),
             lines_normalized: self.lines_normalized.clone(),
             text_processed: Some(processed),
+            copyright_lines: self.copyright_lines.clone(),
         })
     }
 
@@ -180,10 +203,101 @@ impl TextData {
         }
     }
 
+    /// Get the copyright/attribution lines stripped out of this text during
+    /// normalization.
+    ///
+    /// These are lines that were recognized as copyright statements (e.g.
+    /// `Copyright 2018 ...`, `(c) ...`, `All rights reserved`) and removed
+    /// before matching so they don't affect the score. They're kept around
+    /// separately so callers can report who holds the copyright alongside
+    /// whatever license was matched.
+    ///
+    /// Returns `None` if the text was discarded with `without_text`.
+    pub fn attribution(&self) -> Option<&[String]> {
+        match self.copyright_lines {
+            Some(ref lines) => Some(lines),
+            None => None,
+        }
+    }
+
+    /// Produce a token-level diff between this text and another.
+    ///
+    /// This walks the normalized lines of both texts, word by word, and
+    /// reports where they agree (`DiffOp::Equal`) and where they diverge
+    /// (`DiffOp::Insert` for tokens only in `self`, `DiffOp::Delete` for
+    /// tokens only in `other`). It's useful for explaining a sub-1.0
+    /// `match_score`: render the result to show exactly which words were
+    /// added, removed, or changed relative to the canonical license text.
+    ///
+    /// `lcs_diff` builds an O(n*m) table in both time and space, where `n`
+    /// and `m` are the token counts of `self` and `other`, so callers should
+    /// narrow the view to something excerpt-sized (e.g. via
+    /// `optimize_bounds`) before diffing rather than diffing a whole,
+    /// unbounded file. To guard against the worst case regardless, this
+    /// returns an error instead of diffing if the table it would need to
+    /// build exceeds `MAX_DIFF_TABLE_CELLS` cells.
+    ///
+    /// Returns an error if either `TextData` was built with `without_text`.
+    pub fn diff(&self, other: &TextData) -> Result<Vec<DiffOp>, Error> {
+        let a = self.diff_tokens()?;
+        let b = other.diff_tokens()?;
+
+        let cells = (a.len() as u64 + 1) * (b.len() as u64 + 1);
+        if cells > MAX_DIFF_TABLE_CELLS {
+            return Err(format_err!(
+                "text is too large to diff ({} tokens x {} tokens); narrow the view first, e.g. with optimize_bounds",
+                a.len(),
+                b.len()
+            ));
+        }
+
+        let a_refs: Vec<&str> = a.iter().map(String::as_str).collect();
+        let b_refs: Vec<&str> = b.iter().map(String::as_str).collect();
+        Ok(lcs_diff(&a_refs, &b_refs))
+    }
+
+    /// Split this text's normalized lines into whitespace-separated tokens,
+    /// for use by `diff`.
+    fn diff_tokens(&self) -> Result<Vec<String>, Error> {
+        match self.lines_normalized {
+            Some(ref lines) => Ok(lines[self.lines_view.0..self.lines_view.1]
+                .join("\n")
+                .split_whitespace()
+                .map(String::from)
+                .collect()),
+            None => Err(format_err!("TextData does not have original text")),
+        }
+    }
+
     /// Compare this `TextData` with another, returning a similarity score.
     ///
-    /// This is what's used during analysis to rank licenses.
+    /// This is what's used during analysis to rank licenses. It's a thin
+    /// wrapper around `match_score_with_opts` using the default length-ratio
+    /// threshold.
     pub fn match_score(&self, other: &TextData) -> f32 {
+        self.match_score_with_opts(other, 0.5)
+    }
+
+    /// Like `match_score`, but exposes the length-ratio gate's threshold.
+    ///
+    /// Before computing the full Dice coefficient, this compares the total
+    /// n-gram occurrence counts of `self` and `other` -- the same counts
+    /// `NgramSet::dice` itself weighs its similarity computation by. If the
+    /// smaller count is below `min_ratio` of the larger, the texts are
+    /// assumed to be too different in size to be a meaningful match, and
+    /// `0.0` is returned immediately without ever calling into
+    /// `NgramSet::dice`. This lets a large `Store` sweep, or
+    /// `optimize_bounds`'s inner search, cheaply discard wildly mismatched
+    /// candidates.
+    ///
+    /// `min_ratio` should be in `[0.0, 1.0]`; `match_score` uses `0.5`.
+    pub fn match_score_with_opts(&self, other: &TextData, min_ratio: f32) -> f32 {
+        let (a, b) = (self.match_data.total(), other.match_data.total());
+        let (small, big) = if a < b { (a, b) } else { (b, a) };
+        if (small as f32) < (big as f32) * min_ratio {
+            return 0.0;
+        }
+
         self.match_data.dice(&other.match_data)
     }
 
@@ -191,6 +305,45 @@ impl TextData {
         self.match_data.eq(&other.match_data)
     }
 
+    /// Get a content hash of this text, suitable for detecting an exact
+    /// (post-normalization) match against another `TextData` in O(1).
+    ///
+    /// This hashes the same aggressively-processed token stream used to
+    /// build the n-grams for `match_score`, so two texts that normalize
+    /// identically will always produce the same hash. The hash is computed
+    /// with a fixed-seed FNV-1a rather than `std`'s randomized
+    /// `DefaultHasher`, so it's stable across runs and processes -- a
+    /// `Store` can serialize a `HashMap<u64, _>` built from these hashes
+    /// alongside the rest of its data and reuse it on the next load.
+    pub fn content_hash(&self) -> u64 {
+        let text = self.text_processed.as_ref().map_or("", String::as_str);
+        fnv1a64(text.as_bytes())
+    }
+
+    /// Compute a MinHash signature of length `k` over this text's n-gram set.
+    ///
+    /// For each of `k` fixed hash seeds, this hashes every n-gram key in the
+    /// underlying match data and keeps the minimum value. The resulting
+    /// signature approximates the Jaccard similarity between two n-gram
+    /// sets: the fraction of seed positions where two signatures agree
+    /// converges to the Jaccard index of the underlying sets as `k` grows.
+    ///
+    /// Banding a signature into groups of rows (see
+    /// `lsh::MinHashIndex`) turns this into a locality-sensitive-hashing
+    /// index that can prune candidates before a full `match_score`
+    /// comparison against a large `Store`.
+    pub fn minhash_signature(&self, k: usize) -> Vec<u64> {
+        (0..k as u64)
+            .map(|seed| {
+                self.match_data
+                    .keys()
+                    .map(|ngram| fnv1a64_seeded(seed, ngram.as_bytes()))
+                    .min()
+                    .unwrap_or_else(u64::max_value)
+            })
+            .collect()
+    }
+
     /// Attempt to optimize a known match to locate possible line ranges.
     ///
     /// Returns a new `TextData` struct and a score. The returned struct is a
@@ -203,9 +356,21 @@ impl TextData {
     /// You should check the value of `lines_view` on the returned struct to
     /// find the line ranges.
     pub fn optimize_bounds(&self, other: &TextData) -> (Self, f32) {
+        // Probe candidate views with `match_score_with_opts(other, 0.0)` rather
+        // than `match_score`: the latter's length-ratio gate zeroes the score
+        // outright once a candidate view's size diverges enough from `other`,
+        // which turns the score curve `search_optimize` expects to be smooth
+        // into a flat-zero plateau it can get lost in. Bypassing the gate here
+        // is fine since we're narrowing towards the real match, not deciding
+        // whether one exists.
+
         // optimize the ending bounds of the text match
         let (end_optimized, _) = self.search_optimize(
-            &|end| self.with_view(0, end).unwrap().match_score(other),
+            &|end| {
+                self.with_view(0, end)
+                    .unwrap()
+                    .match_score_with_opts(other, 0.0)
+            },
             &|end| self.with_view(0, end).unwrap(),
         );
         let new_end = end_optimized.lines_view.1;
@@ -216,7 +381,7 @@ impl TextData {
                 end_optimized
                     .with_view(start, new_end)
                     .unwrap()
-                    .match_score(other)
+                    .match_score_with_opts(other, 0.0)
             },
             &|start| end_optimized.with_view(start, new_end).unwrap(),
         );
@@ -254,6 +419,79 @@ impl TextData {
     }
 }
 
+/// Compute a token-level diff between two token streams using the classic
+/// dynamic-programming LCS algorithm, then walk the backtrace to build up
+/// a sequence of `DiffOp`.
+///
+/// This is O(n*m) in both time and space; `TextData::diff` is responsible
+/// for guarding against pathologically large inputs before calling this.
+fn lcs_diff(a: &[&str], b: &[&str]) -> Vec<DiffOp> {
+    let (n, m) = (a.len(), b.len());
+    let mut table = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i][j] = if a[i] == b[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a[i] == b[j] {
+            ops.push(DiffOp::Equal(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            ops.push(DiffOp::Insert(a[i].to_string()));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Delete(b[j].to_string()));
+            j += 1;
+        }
+    }
+    while i < n {
+        ops.push(DiffOp::Insert(a[i].to_string()));
+        i += 1;
+    }
+    while j < m {
+        ops.push(DiffOp::Delete(b[j].to_string()));
+        j += 1;
+    }
+
+    ops
+}
+
+/// A fixed-seed FNV-1a hash, used by `TextData::content_hash`.
+///
+/// This is deliberately not `std::collections::hash_map::DefaultHasher`: that
+/// hasher is randomly seeded per-process, so its output can't be persisted
+/// and compared across runs. FNV-1a is simple enough to implement directly
+/// here without pulling in another dependency.
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// A variant of `fnv1a64` mixed with a seed, giving a distinct hash function
+/// per seed value. Used to build the `k` independent hash functions a
+/// MinHash signature needs.
+fn fnv1a64_seeded(seed: u64, bytes: &[u8]) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let offset_basis = 0xcbf2_9ce4_8422_2325 ^ seed.wrapping_mul(PRIME);
+
+    bytes.iter().fold(offset_basis, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
 impl<'a> From<&'a str> for TextData {
     fn from(text: &'a str) -> Self {
         TextData::new(text)
@@ -273,6 +511,109 @@ mod tests {
     // psst:
     // cargo test -- --nocapture
 
+    #[test]
+    fn test_diff() {
+        let license = TextData::from("this is a test license\nwith two lines");
+        let modified = TextData::from("this is a test license\nwith three lines");
+
+        let diff = license.diff(&modified).unwrap();
+        assert_eq!(
+            diff,
+            vec![
+                DiffOp::Equal("this".into()),
+                DiffOp::Equal("is".into()),
+                DiffOp::Equal("a".into()),
+                DiffOp::Equal("test".into()),
+                DiffOp::Equal("license".into()),
+                DiffOp::Equal("with".into()),
+                DiffOp::Insert("two".into()),
+                DiffOp::Delete("three".into()),
+                DiffOp::Equal("lines".into()),
+            ]
+        );
+
+        let without_text = TextData::from("no text here").without_text();
+        assert!(without_text.diff(&license).is_err());
+    }
+
+    #[test]
+    fn test_diff_too_large() {
+        // neither side is remotely close to a real license file, but the
+        // token counts alone are enough to blow past the table size guard
+        let a = TextData::from(vec!["word"; 9_000].join(" ").as_str());
+        let b = TextData::from(vec!["other"; 9_000].join(" ").as_str());
+        assert!(a.diff(&b).is_err());
+    }
+
+    #[test]
+    fn test_attribution() {
+        let text = TextData::from(
+            "Copyright 2018 Some Author\n\
+             (c) 2019 Another Author\n\
+             © 2020 Yet Another\n\
+             All rights reserved.\n\
+             \n\
+             This is the actual license text.",
+        );
+
+        assert_eq!(
+            text.attribution(),
+            Some(
+                vec![
+                    "Copyright 2018 Some Author".to_string(),
+                    "(c) 2019 Another Author".to_string(),
+                    "© 2020 Yet Another".to_string(),
+                    "All rights reserved.".to_string(),
+                ].as_slice()
+            )
+        );
+
+        // copyright lines are blanked out of the normalized lines, not left
+        // in place to pollute matching
+        let lines = text.lines().unwrap();
+        assert!(lines.iter().all(|line| !line.contains("Author")));
+        assert_eq!(lines[5], "This is the actual license text.");
+
+        let without_text = text.without_text();
+        assert_eq!(without_text.attribution(), None);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let a = TextData::from("My First License\ncopyright 2018 someone");
+        let b = TextData::from("copyright 2020 someone else\n\nmy first license");
+        let c = TextData::from("My Second License");
+
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_ne!(a.content_hash(), c.content_hash());
+    }
+
+    #[test]
+    fn test_minhash_signature() {
+        let a = TextData::from("My First License\nwith some extra words in it");
+        let b = TextData::from("my first license, with some extra words in it!");
+        let c = TextData::from("Something else entirely, not a license at all");
+
+        assert_eq!(a.minhash_signature(32), b.minhash_signature(32));
+        assert_ne!(a.minhash_signature(32), c.minhash_signature(32));
+    }
+
+    #[test]
+    fn test_match_score_length_gate() {
+        let short = TextData::from("a short license text");
+        let long = TextData::from(
+            "a much, much longer license text that goes on for quite a while \
+             with plenty of extra words padding it out well past the point \
+             where it could reasonably be mistaken for the short one",
+        );
+
+        // wildly different sizes: the default gate short-circuits to 0.0
+        assert_eq!(short.match_score(&long), 0.0);
+
+        // a permissive ratio lets the real Dice coefficient through instead
+        assert!(short.match_score_with_opts(&long, 0.0) > 0.0);
+    }
+
     #[test]
     fn test_optimize_bounds() {
         let license_text = "this is a license text\nor it pretends to be one\nit's just a test";
@@ -307,4 +648,38 @@ mod tests {
             "bounds are (4, 7) or (4, 8)"
         );
     }
+
+    #[test]
+    fn test_optimize_bounds_large_file() {
+        // a short license embedded in a realistically-sized surrounding file;
+        // large enough that `match_score`'s default length-ratio gate would
+        // zero out the probed score once the view grows past the license's
+        // size, which used to send `optimize_bounds` completely off the rails
+        let license_text = "this is a license text\nor it pretends to be one\nit's just a test";
+        let license = TextData::from(license_text).without_text();
+
+        let mut lines: Vec<String> = vec![
+            "// some header comment".to_string(),
+            String::new(),
+            "this is a license text".to_string(),
+            "or it pretends to be one".to_string(),
+            "it's just a test".to_string(),
+            String::new(),
+        ];
+        for i in 0..200 {
+            lines.push(format!("fn function_{}() {{ /* filler code */ }}", i));
+        }
+        let sample_text = lines.join("\n");
+        let sample = TextData::from(sample_text.as_str());
+
+        let (optimized, score) = sample.optimize_bounds(&license);
+        println!("{:?}", optimized.lines_view);
+        // end bounds of 5 and 6 score the same, since line 5 is blank (not
+        // counted) -- same caveat as in `test_optimize_bounds` above.
+        assert!(
+            (2, 5) == optimized.lines_view || (2, 6) == optimized.lines_view,
+            "bounds are (2, 5) or (2, 6)"
+        );
+        assert!(score > 0.99f32, "license within large file matches");
+    }
 }
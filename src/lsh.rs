@@ -0,0 +1,109 @@
+// Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License").
+// You may not use this file except in compliance with the License.
+// A copy of the License is located at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// or in the "license" file accompanying this file. This file is distributed
+// on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing
+// permissions and limitations under the License.
+
+//! A locality-sensitive-hashing (LSH) index over `TextData` MinHash
+//! signatures, used to prune candidates before an exact Dice comparison.
+
+use std::collections::{HashMap, HashSet};
+
+/// A banded MinHash index: signatures are split into `bands` groups of
+/// `rows` values each, and items that share a band's hash are bucketed
+/// together. Two signatures are very likely to collide in at least one band
+/// if their underlying sets are similar, and very unlikely to if they're
+/// not -- that's what makes this useful as a cheap pre-filter.
+///
+/// The probability that two items with Jaccard similarity `s` collide in at
+/// least one band follows an S-curve, `1 - (1 - s^rows)^bands`, which crosses
+/// 0.5 near `s ~= (1 / bands)^(1 / rows)`. Pick `bands` and `rows` (with
+/// `bands * rows == k`, the signature length) so that threshold lines up
+/// with the similarity you care about -- e.g. `bands = 8, rows = 16` for
+/// `k = 128` gives a threshold around 0.85.
+pub struct MinHashIndex {
+    bands: usize,
+    rows: usize,
+    buckets: Vec<HashMap<u64, Vec<usize>>>,
+}
+
+impl MinHashIndex {
+    /// Create an empty index banding `bands * rows`-length signatures into
+    /// `bands` bands of `rows` rows each.
+    pub fn new(bands: usize, rows: usize) -> MinHashIndex {
+        MinHashIndex {
+            bands,
+            rows,
+            buckets: (0..bands).map(|_| HashMap::new()).collect(),
+        }
+    }
+
+    /// Insert an item's MinHash signature into the index under the given id.
+    pub fn insert(&mut self, id: usize, signature: &[u64]) {
+        debug_assert_eq!(signature.len(), self.bands * self.rows);
+        let rows = self.rows;
+        for (band, bucket) in self.buckets.iter_mut().enumerate() {
+            let start = band * rows;
+            let key = hash_band(&signature[start..start + rows]);
+            bucket.entry(key).or_insert_with(Vec::new).push(id);
+        }
+    }
+
+    /// Collect every id that shares at least one band bucket with the given
+    /// signature -- the candidate set to run an exact comparison on.
+    pub fn candidates(&self, signature: &[u64]) -> Vec<usize> {
+        debug_assert_eq!(signature.len(), self.bands * self.rows);
+        let mut seen = HashSet::new();
+        let mut candidates = Vec::new();
+
+        for (band, bucket) in self.buckets.iter().enumerate() {
+            let start = band * self.rows;
+            let key = hash_band(&signature[start..start + self.rows]);
+            if let Some(ids) = bucket.get(&key) {
+                for &id in ids {
+                    if seen.insert(id) {
+                        candidates.push(id);
+                    }
+                }
+            }
+        }
+
+        candidates
+    }
+}
+
+/// Combine a band's rows into a single bucket key.
+fn hash_band(rows: &[u64]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    rows.iter().fold(OFFSET_BASIS, |hash, &row| {
+        (hash ^ row).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let mut index = MinHashIndex::new(4, 2);
+        index.insert(0, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        index.insert(1, &[1, 2, 3, 4, 9, 9, 9, 9]);
+        index.insert(2, &[9, 9, 9, 9, 9, 9, 9, 9]);
+
+        // shares the first two bands with id 0 and 1, none with id 2
+        let candidates = index.candidates(&[1, 2, 3, 4, 0, 0, 0, 0]);
+        assert!(candidates.contains(&0));
+        assert!(candidates.contains(&1));
+        assert!(!candidates.contains(&2));
+    }
+}
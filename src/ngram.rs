@@ -0,0 +1,99 @@
+// Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License").
+// You may not use this file except in compliance with the License.
+// A copy of the License is located at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// or in the "license" file accompanying this file. This file is distributed
+// on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing
+// permissions and limitations under the License.
+
+//! N-gram sets used for Dice-coefficient text similarity.
+
+use std::collections::HashMap;
+
+/// A set of word n-grams extracted from a piece of text, with the number of
+/// times each one occurred.
+///
+/// This is the data structure `TextData::match_score` compares with the Dice
+/// coefficient.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NgramSet {
+    n: usize,
+    ngrams: HashMap<String, u32>,
+}
+
+impl NgramSet {
+    /// Build an n-gram set of size `n` from the words in `text`.
+    pub fn from_str(text: &str, n: usize) -> NgramSet {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut ngrams = HashMap::new();
+
+        if words.len() >= n {
+            for window in words.windows(n) {
+                *ngrams.entry(window.join(" ")).or_insert(0) += 1;
+            }
+        }
+
+        NgramSet { n, ngrams }
+    }
+
+    /// The `n` this set was built with.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// The number of distinct n-grams in this set.
+    pub fn len(&self) -> usize {
+        self.ngrams.len()
+    }
+
+    /// The total number of n-gram occurrences in this set (i.e. summed over
+    /// every distinct n-gram's count), which is what `dice` actually weighs
+    /// its similarity computation by.
+    pub fn total(&self) -> u32 {
+        self.ngrams.values().sum()
+    }
+
+    /// Whether this set has no n-grams at all.
+    pub fn is_empty(&self) -> bool {
+        self.ngrams.is_empty()
+    }
+
+    /// Iterate over the distinct n-gram keys in this set.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.ngrams.keys()
+    }
+
+    /// Compute the Sorensen-Dice coefficient between this set and another,
+    /// a measure of similarity in `[0.0, 1.0]`.
+    pub fn dice(&self, other: &NgramSet) -> f32 {
+        let self_total = self.total();
+        let other_total = other.total();
+
+        if self_total == 0 && other_total == 0 {
+            return 1.0;
+        }
+        if self_total == 0 || other_total == 0 {
+            return 0.0;
+        }
+
+        let common: u32 = self
+            .ngrams
+            .iter()
+            .map(|(key, &count)| match other.ngrams.get(key) {
+                Some(&other_count) => count.min(other_count),
+                None => 0,
+            })
+            .sum();
+
+        (2 * common) as f32 / (self_total + other_total) as f32
+    }
+
+    pub(crate) fn eq(&self, other: &NgramSet) -> bool {
+        self.n == other.n && self.ngrams == other.ngrams
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License").
+// You may not use this file except in compliance with the License.
+// A copy of the License is located at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// or in the "license" file accompanying this file. This file is distributed
+// on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing
+// permissions and limitations under the License.
+
+//! Text normalization used to prepare input for matching.
+//!
+//! There are two stages here: a line-based pass (`apply_normalizers`) that
+//! tidies up whitespace and incidental formatting while preserving the number
+//! of lines (so line numbers from the original text still line up for
+//! `TextData::optimize_bounds`), and an aggressive pass (`apply_aggressive`)
+//! that throws away everything but the words themselves for n-gram scoring.
+
+use regex::Regex;
+
+lazy_static! {
+    // Lines that are almost certainly a copyright/attribution statement
+    // rather than license text. These are dropped from the normalized text
+    // so they don't pollute matching, but the matched text itself is worth
+    // keeping around -- see `apply_normalizers`.
+    static ref COPYRIGHT_LINE: Regex = Regex::new(
+        r"(?i)^\s*(copyright\s|copyright$|\(c\)|©|all rights reserved).*$"
+    ).unwrap();
+
+    static ref HORIZONTAL_WHITESPACE: Regex = Regex::new(r"[ \t]+").unwrap();
+    static ref NON_ALPHANUMERIC: Regex = Regex::new(r"[^a-z0-9]+").unwrap();
+}
+
+/// Run the line-based normalizers over the given text.
+///
+/// This collapses incidental whitespace and blanks out lines that are
+/// recognized as copyright/attribution statements, but otherwise preserves
+/// line structure: the returned `Vec` has exactly as many entries as the
+/// input has lines. The copyright lines that were found and blanked out are
+/// returned alongside, in the order they appeared.
+pub fn apply_normalizers(text: &str) -> (Vec<String>, Vec<String>) {
+    let mut copyrights = Vec::new();
+
+    let lines = text
+        .lines()
+        .map(|line| {
+            let line = HORIZONTAL_WHITESPACE.replace_all(line.trim(), " ").to_string();
+            if COPYRIGHT_LINE.is_match(&line) {
+                copyrights.push(line);
+                String::new()
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    (lines, copyrights)
+}
+
+/// Aggressively normalize a blob of text for n-gram matching.
+///
+/// This lowercases the text and strips everything that isn't an ASCII letter
+/// or digit (non-ASCII letters are stripped too), collapsing the result down to single-space-separated words. The output is
+/// not meant to be human readable; it exists purely to make matching
+/// resilient to formatting differences between otherwise-identical license
+/// texts.
+pub fn apply_aggressive(text: &str) -> String {
+    let lower = text.to_lowercase();
+    NON_ALPHANUMERIC
+        .replace_all(&lower, " ")
+        .trim()
+        .to_string()
+}
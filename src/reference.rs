@@ -0,0 +1,131 @@
+// Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License").
+// You may not use this file except in compliance with the License.
+// A copy of the License is located at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// or in the "license" file accompanying this file. This file is distributed
+// on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing
+// permissions and limitations under the License.
+
+//! Detecting *references* to a license -- an SPDX tag or a license name --
+//! in text that doesn't contain the license's full body.
+
+use aho_corasick::AhoCorasickBuilder;
+
+const SPDX_TAG: &str = "spdx-license-identifier:";
+
+/// Scans normalized text for references to known licenses, without
+/// requiring the full license text to be present.
+///
+/// This looks for two things: `SPDX-License-Identifier:` tags, and known
+/// license name/alias phrases (e.g. "Apache License, Version 2.0", "MIT
+/// License", "See LICENSE for details"). The phrase set is compiled once
+/// into a single Aho-Corasick automaton so a scan is one pass over the text
+/// rather than one substring search per phrase.
+pub struct ReferenceMatcher {
+    automaton: aho_corasick::AhoCorasick,
+    ids: Vec<String>,
+}
+
+impl ReferenceMatcher {
+    /// Build a matcher from `(phrase, spdx_id)` pairs -- typically every
+    /// license name/alias a `Store` knows about, each paired with the SPDX
+    /// id it should resolve to.
+    pub fn new(phrases: &[(String, String)]) -> ReferenceMatcher {
+        let patterns: Vec<&str> = phrases.iter().map(|&(ref phrase, _)| phrase.as_str()).collect();
+        let automaton = AhoCorasickBuilder::new()
+            .ascii_case_insensitive(true)
+            .build(&patterns);
+
+        ReferenceMatcher {
+            automaton,
+            ids: phrases.iter().map(|&(_, ref id)| id.clone()).collect(),
+        }
+    }
+
+    /// Scan a text's normalized lines, returning every distinct SPDX id
+    /// referenced, either via an explicit tag or a recognized license
+    /// phrase.
+    pub fn scan(&self, lines: &[String]) -> Vec<String> {
+        let mut found = Vec::new();
+
+        for line in lines {
+            if let Some(id) = scan_spdx_tag(line) {
+                if !found.contains(&id) {
+                    found.push(id);
+                }
+            }
+        }
+
+        let joined = lines.join("\n");
+        for mat in self.automaton.find_iter(&joined) {
+            let id = &self.ids[mat.pattern()];
+            if !found.contains(id) {
+                found.push(id.clone());
+            }
+        }
+
+        found
+    }
+}
+
+/// Look for an `SPDX-License-Identifier:` tag on a single line, returning
+/// the id that follows it, if any.
+fn scan_spdx_tag(line: &str) -> Option<String> {
+    // `SPDX_TAG` is pure ASCII, so `to_ascii_lowercase` (which only touches
+    // ASCII bytes and leaves every other byte untouched) keeps the result
+    // the same length, byte-for-byte, as `line`. Plain `to_lowercase` can
+    // change the byte length of non-ASCII characters, which would desync
+    // the offset found in the lowercased copy from `line` itself.
+    let lower = line.to_ascii_lowercase();
+    let pos = lower.find(SPDX_TAG)?;
+    let rest = line[pos + SPDX_TAG.len()..].trim();
+    let id: String = rest.chars().take_while(|c| !c.is_whitespace()).collect();
+    if id.is_empty() {
+        None
+    } else {
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_spdx_tag() {
+        let matcher = ReferenceMatcher::new(&[]);
+        let lines = vec!["// SPDX-License-Identifier: MIT".to_string()];
+        assert_eq!(matcher.scan(&lines), vec!["MIT".to_string()]);
+    }
+
+    #[test]
+    fn test_scan_spdx_tag_non_ascii_prefix() {
+        let matcher = ReferenceMatcher::new(&[]);
+
+        // a multi-byte-lowercasing character ahead of the tag must not
+        // desync the byte offset used to slice the id out
+        let lines = vec!["İİSPDX-License-Identifier:MITXYZ".to_string()];
+        assert_eq!(matcher.scan(&lines), vec!["MITXYZ".to_string()]);
+
+        // nor land the slice inside another multi-byte character
+        let lines = vec!["İSPDX-License-Identifier:é-license".to_string()];
+        assert_eq!(matcher.scan(&lines), vec!["é-license".to_string()]);
+    }
+
+    #[test]
+    fn test_scan_phrase() {
+        let matcher = ReferenceMatcher::new(&[
+            ("Apache License, Version 2.0".to_string(), "Apache-2.0".to_string()),
+            ("MIT License".to_string(), "MIT".to_string()),
+        ]);
+        let lines = vec![
+            "Licensed under the Apache License, Version 2.0 (the \"License\")".to_string(),
+        ];
+        assert_eq!(matcher.scan(&lines), vec!["Apache-2.0".to_string()]);
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License").
+// You may not use this file except in compliance with the License.
+// A copy of the License is located at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// or in the "license" file accompanying this file. This file is distributed
+// on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing
+// permissions and limitations under the License.
+
+//! A `Store` holds a collection of known licenses to match unknown text
+//! against.
+
+use std::collections::HashMap;
+
+use license::{LicenseType, TextData};
+use lsh::MinHashIndex;
+use reference::ReferenceMatcher;
+
+// Signature length and banding used to build each Store's MinHash index.
+// 8 bands of 16 rows gives an S-curve threshold around 0.85 Jaccard
+// similarity -- see `lsh::MinHashIndex` for how that's derived.
+const MINHASH_K: usize = 128;
+const MINHASH_BANDS: usize = 8;
+const MINHASH_ROWS: usize = 16;
+
+/// Descriptive phrases known to refer to a given SPDX id, for seeding
+/// `Store::build_reference_matcher`.
+///
+/// Bare SPDX ids (`"MIT"`, `"ISC"`, ...) are deliberately not used as search
+/// phrases on their own: several of them are also common English word
+/// fragments ("submit", "discuss"), which would make reference detection
+/// false-positive on ordinary source/prose text. Every phrase here is a
+/// full license name/alias, so a match is actually meaningful. An id with no
+/// entry here falls back to `"<id> License"` in `build_reference_matcher`,
+/// which is still far less prone to spurious hits than the bare id.
+fn known_aliases(id: &str) -> &'static [&'static str] {
+    match id {
+        "MIT" => &["MIT License", "Expat License"],
+        "Apache-2.0" => &[
+            "Apache License, Version 2.0",
+            "Apache License 2.0",
+            "Apache-2.0 License",
+        ],
+        "ISC" => &["ISC License"],
+        "BSD-2-Clause" => &["BSD 2-Clause License", "Simplified BSD License"],
+        "BSD-3-Clause" => &[
+            "BSD 3-Clause License",
+            "New BSD License",
+            "Modified BSD License",
+        ],
+        // Deliberately no bare "GPLv2"/"GPLv3" alias here: both are substrings
+        // of other real license families' names ("LGPLv2", "AGPLv3", ...), so
+        // an unanchored Aho-Corasick match on them false-positives on text
+        // that never mentions GPL at all.
+        "GPL-2.0" => &["GNU General Public License, Version 2", "GNU GPL v2"],
+        "GPL-3.0" => &["GNU General Public License, Version 3", "GNU GPL v3"],
+        "LGPL-3.0" => &["GNU Lesser General Public License, Version 3"],
+        "MPL-2.0" => &[
+            "Mozilla Public License, Version 2.0",
+            "Mozilla Public License 2.0",
+        ],
+        "Unlicense" => &["The Unlicense"],
+        _ => &[],
+    }
+}
+
+/// How a `Match` was found.
+#[derive(Clone, PartialEq, Debug)]
+pub enum MatchKind {
+    /// The full license text was found and scored against known licenses.
+    Dice,
+    /// Only a reference to the license was found (an `SPDX-License-Identifier`
+    /// tag, or a recognized license name/alias), not its full text.
+    Reference,
+}
+
+/// The result of analyzing a text against a `Store`.
+#[derive(Clone, Debug)]
+pub struct Match {
+    /// The name of the matched license.
+    pub name: String,
+    /// Which variant of the license text was matched, if a text body was
+    /// matched at all. This is `None` for a `MatchKind::Reference` match,
+    /// since those only identify a license by name/tag and never compare
+    /// against any stored license text.
+    pub license_type: Option<LicenseType>,
+    /// The similarity score of the match, from 0 to 1.
+    pub score: f32,
+    /// How this match was found.
+    pub kind: MatchKind,
+}
+
+/// A collection of known licenses, identified by name and `LicenseType`.
+///
+/// This is the entry point for matching an unknown text against a set of
+/// known licenses.
+pub struct Store {
+    licenses: HashMap<String, HashMap<LicenseType, TextData>>,
+
+    // Maps a `TextData::content_hash` to the license it came from, so an
+    // exact (post-normalization) match can be identified in O(1) without
+    // falling back to the full Dice comparison sweep below.
+    hash_index: HashMap<u64, (String, LicenseType)>,
+
+    // Maps an id (index into `entries`) to a (name, license_type) pair, and
+    // an LSH index over those same ids, so `analyze` only has to run the
+    // exact Dice score against a small candidate set instead of every
+    // license in the store.
+    entries: Vec<(String, LicenseType)>,
+    minhash_index: MinHashIndex,
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Store::new()
+    }
+}
+
+impl Store {
+    /// Create a new, empty `Store`.
+    pub fn new() -> Store {
+        Store {
+            licenses: HashMap::new(),
+            hash_index: HashMap::new(),
+            entries: Vec::new(),
+            minhash_index: MinHashIndex::new(MINHASH_BANDS, MINHASH_ROWS),
+        }
+    }
+
+    /// Add a license's text to the store under the given name and type.
+    pub fn add_license(&mut self, name: String, license_type: LicenseType, data: TextData) {
+        self.hash_index
+            .insert(data.content_hash(), (name.clone(), license_type.clone()));
+
+        let id = self.entries.len();
+        self.entries.push((name.clone(), license_type.clone()));
+        self.minhash_index
+            .insert(id, &data.minhash_signature(MINHASH_K));
+
+        self.licenses
+            .entry(name)
+            .or_insert_with(HashMap::new)
+            .insert(license_type, data);
+    }
+
+    /// Get the licenses currently held in this store.
+    pub fn licenses(&self) -> &HashMap<String, HashMap<LicenseType, TextData>> {
+        &self.licenses
+    }
+
+    /// Analyze an unknown text against the licenses in the store, returning
+    /// the best match.
+    ///
+    /// If the text is an exact match (after normalization) for a stored
+    /// license, that license is returned immediately with a score of `1.0`
+    /// without computing any Dice coefficients. Otherwise, the text's
+    /// MinHash signature is used to look up a small set of candidate
+    /// licenses from the LSH index, and only those are scored with the
+    /// exact Dice coefficient.
+    pub fn analyze(&self, text: &TextData) -> Option<Match> {
+        if let Some(&(ref name, ref license_type)) = self.hash_index.get(&text.content_hash()) {
+            return Some(Match {
+                name: name.clone(),
+                license_type: Some(license_type.clone()),
+                score: 1.0,
+                kind: MatchKind::Dice,
+            });
+        }
+
+        let signature = text.minhash_signature(MINHASH_K);
+        self.minhash_index
+            .candidates(&signature)
+            .into_iter()
+            .filter_map(|id| self.entries.get(id))
+            .filter_map(|(name, license_type)| {
+                let data = self.licenses.get(name)?.get(license_type)?;
+                Some(Match {
+                    name: name.clone(),
+                    license_type: Some(license_type.clone()),
+                    score: text.match_score(data),
+                    kind: MatchKind::Dice,
+                })
+            })
+            .fold(None, |best: Option<Match>, candidate| match best {
+                Some(ref b) if b.score >= candidate.score => best,
+                _ => Some(candidate),
+            })
+    }
+
+    /// Build a `ReferenceMatcher` over every license name known to this
+    /// store, for use with `analyze_reference`.
+    ///
+    /// This is a separate, explicit build step (rather than something
+    /// `analyze` does automatically) because compiling the Aho-Corasick
+    /// automaton has a cost proportional to the number of licenses in the
+    /// store; callers that only want Dice-based matching shouldn't pay it.
+    pub fn build_reference_matcher(&self) -> ReferenceMatcher {
+        let phrases: Vec<(String, String)> = self
+            .licenses
+            .keys()
+            .flat_map(|name| {
+                let aliases = known_aliases(name);
+                if aliases.is_empty() {
+                    vec![(format!("{} License", name), name.clone())]
+                } else {
+                    aliases
+                        .iter()
+                        .map(|alias| (alias.to_string(), name.clone()))
+                        .collect()
+                }
+            })
+            .collect();
+        ReferenceMatcher::new(&phrases)
+    }
+
+    /// Scan a text for references to known licenses -- an
+    /// `SPDX-License-Identifier` tag or a recognized license name -- rather
+    /// than its full text.
+    ///
+    /// This gives useful answers for files that `analyze` would otherwise
+    /// rate near zero: a one-line SPDX tag or a sentence like "Licensed
+    /// under the Apache License, Version 2.0" has almost no n-gram overlap
+    /// with the canonical license text, but unambiguously tells you which
+    /// license applies.
+    pub fn analyze_reference(&self, text: &TextData, matcher: &ReferenceMatcher) -> Vec<Match> {
+        let lines = match text.lines() {
+            Some(lines) => lines,
+            None => return Vec::new(),
+        };
+
+        matcher
+            .scan(lines)
+            .into_iter()
+            .map(|name| Match {
+                name,
+                license_type: None,
+                score: 1.0,
+                kind: MatchKind::Reference,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_reference_phrase_and_tag() {
+        let mut store = Store::new();
+        store.add_license(
+            "MIT".to_string(),
+            LicenseType::Original,
+            TextData::from("MIT License\n\npermission is hereby granted..."),
+        );
+        let matcher = store.build_reference_matcher();
+
+        let phrase = TextData::from("Licensed under the MIT License.\nfn main() {}");
+        let matches = store.analyze_reference(&phrase, &matcher);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].name, "MIT");
+        assert_eq!(matches[0].kind, MatchKind::Reference);
+        assert_eq!(matches[0].license_type, None);
+
+        let tag = TextData::from("// SPDX-License-Identifier: MIT\nfn main() {}");
+        let matches = store.analyze_reference(&tag, &matcher);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].name, "MIT");
+    }
+
+    #[test]
+    fn test_analyze_reference_no_false_positive_on_substring() {
+        let mut store = Store::new();
+        store.add_license(
+            "MIT".to_string(),
+            LicenseType::Original,
+            TextData::from("MIT License\n\npermission is hereby granted..."),
+        );
+        let matcher = store.build_reference_matcher();
+
+        // "submit" contains "mit", but this text references no license at all
+        let text = TextData::from("please submit your pull request before you commit further changes");
+        assert!(store.analyze_reference(&text, &matcher).is_empty());
+    }
+
+    #[test]
+    fn test_analyze_reference_no_false_positive_on_gpl_variant() {
+        let mut store = Store::new();
+        store.add_license(
+            "GPL-2.0".to_string(),
+            LicenseType::Original,
+            TextData::from("GNU General Public License, Version 2\n\nterms and conditions..."),
+        );
+        let matcher = store.build_reference_matcher();
+
+        // "LGPLv2" contains "GPLv2", but this text references the LGPL, not
+        // the GPL -- it must not be reported as a GPL-2.0 match
+        let text = TextData::from("Licensed under LGPLv2, see COPYING.LESSER for details.");
+        assert!(store.analyze_reference(&text, &matcher).is_empty());
+    }
+}